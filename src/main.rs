@@ -1,6 +1,6 @@
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone)]
 struct TcpSegment {
@@ -10,7 +10,21 @@ struct TcpSegment {
     ack: u32,
     syn: bool,
     ack_flag: bool,
+    fin: bool,
+    rst: bool,
+    /// TCP checksum over the pseudo-header, this header (with this field
+    /// zeroed) and the payload. Zero until `with_checksum` fills it in.
+    checksum: u16,
     payload: Vec<u8>,
+    /// Up to three `(start, end)` byte ranges already received above the
+    /// cumulative `ack`, carried as a SACK option.
+    sack_blocks: Vec<(u32, u32)>,
+    /// Send-side timestamp on a data segment, used by the receiver to
+    /// measure one-way delay for LEDBAT.
+    timestamp: Option<Instant>,
+    /// One-way delay the receiver measured for the segment being acked,
+    /// echoed back so the sender can track queuing delay.
+    delay_sample: Option<Duration>,
 }
 
 impl TcpSegment {
@@ -22,7 +36,13 @@ impl TcpSegment {
             ack: 0,
             syn: true,
             ack_flag: false,
+            fin: false,
+            rst: false,
+            checksum: 0,
             payload: vec![],
+            sack_blocks: vec![],
+            timestamp: None,
+            delay_sample: None,
         }
     }
 
@@ -34,7 +54,13 @@ impl TcpSegment {
             ack,
             syn: true,
             ack_flag: true,
+            fin: false,
+            rst: false,
+            checksum: 0,
             payload: vec![],
+            sack_blocks: vec![],
+            timestamp: None,
+            delay_sample: None,
         }
     }
 
@@ -46,7 +72,20 @@ impl TcpSegment {
             ack,
             syn: false,
             ack_flag: true,
+            fin: false,
+            rst: false,
+            checksum: 0,
             payload: vec![],
+            sack_blocks: vec![],
+            timestamp: None,
+            delay_sample: None,
+        }
+    }
+
+    fn ack_with_sack(src: u16, dst: u16, seq: u32, ack: u32, sack_blocks: Vec<(u32, u32)>) -> Self {
+        Self {
+            sack_blocks,
+            ..TcpSegment::ack(src, dst, seq, ack)
         }
     }
 
@@ -58,30 +97,207 @@ impl TcpSegment {
             ack,
             syn: false,
             ack_flag: true,
+            fin: false,
+            rst: false,
+            checksum: 0,
             payload: data,
+            sack_blocks: vec![],
+            timestamp: None,
+            delay_sample: None,
+        }
+    }
+
+    fn fin(src: u16, dst: u16, seq: u32, ack: u32) -> Self {
+        Self {
+            src_port: src,
+            dst_port: dst,
+            seq,
+            ack,
+            syn: false,
+            ack_flag: true,
+            fin: true,
+            rst: false,
+            checksum: 0,
+            payload: vec![],
+            sack_blocks: vec![],
+            timestamp: None,
+            delay_sample: None,
+        }
+    }
+
+    fn rst(src: u16, dst: u16, seq: u32) -> Self {
+        Self {
+            src_port: src,
+            dst_port: dst,
+            seq,
+            ack: 0,
+            syn: false,
+            ack_flag: false,
+            fin: false,
+            rst: true,
+            checksum: 0,
+            payload: vec![],
+            sack_blocks: vec![],
+            timestamp: None,
+            delay_sample: None,
+        }
+    }
+
+    /// Encodes up to three SACK blocks as TCP option kind 5, padded with NOPs
+    /// to a 32-bit boundary. Empty when there's nothing to report.
+    fn encode_sack_option(&self) -> Vec<u8> {
+        if self.sack_blocks.is_empty() {
+            return vec![];
+        }
+        let blocks = &self.sack_blocks[..self.sack_blocks.len().min(3)];
+        let mut opt = vec![5u8, (2 + 8 * blocks.len()) as u8];
+        for (start, end) in blocks {
+            opt.extend_from_slice(&start.to_be_bytes());
+            opt.extend_from_slice(&end.to_be_bytes());
         }
+        while opt.len() % 4 != 0 {
+            opt.push(1); // NOP padding
+        }
+        opt
     }
 
-    fn to_bytes(&self) -> [u8; 20] {
-        let mut buf = [0u8; 20];
+    fn to_bytes(&self) -> Vec<u8> {
+        let options = self.encode_sack_option();
+        let header_words = 5 + (options.len() / 4) as u8;
+        let mut buf = vec![0u8; header_words as usize * 4];
         buf[0..2].copy_from_slice(&self.src_port.to_be_bytes());
         buf[2..4].copy_from_slice(&self.dst_port.to_be_bytes());
         buf[4..8].copy_from_slice(&self.seq.to_be_bytes());
         buf[8..12].copy_from_slice(&self.ack.to_be_bytes());
-        buf[12] = 0x50;
-        buf[13] = (if self.syn { 0x02 } else { 0x00 }) | (if self.ack_flag { 0x10 } else { 0x00 });
+        buf[12] = header_words << 4;
+        buf[13] = (if self.fin { 0x01 } else { 0x00 })
+            | (if self.syn { 0x02 } else { 0x00 })
+            | (if self.rst { 0x04 } else { 0x00 })
+            | (if self.ack_flag { 0x10 } else { 0x00 });
         buf[14..16].copy_from_slice(&64240u16.to_be_bytes());
-        buf[16..18].copy_from_slice(&0u16.to_be_bytes());
+        buf[16..18].copy_from_slice(&self.checksum.to_be_bytes());
         buf[18..20].copy_from_slice(&0u16.to_be_bytes());
+        buf[20..20 + options.len()].copy_from_slice(&options);
         buf
     }
 
+    /// Computes the real TCP checksum (pseudo-header + header-with-checksum
+    /// zeroed + payload) and stores it in the header's checksum field,
+    /// rather than in the payload, so the wire bytes this produces are
+    /// valid for a real kernel stack to validate.
     fn with_checksum(mut self, src_ip: [u8; 4], dst_ip: [u8; 4]) -> Self {
-        let checksum = compute_tcp_checksum(&self, src_ip, dst_ip);
-        self.payload.insert(0, (checksum >> 8) as u8);
-        self.payload.insert(1, (checksum & 0xFF) as u8);
+        self.checksum = compute_tcp_checksum(&self, src_ip, dst_ip);
+        self
+    }
+
+    fn with_timestamp(mut self, timestamp: Instant) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    fn with_delay_sample(mut self, delay_sample: Duration) -> Self {
+        self.delay_sample = Some(delay_sample);
         self
     }
+
+    /// Parses a captured TCP segment (no IP header) back from the wire
+    /// format produced by `to_bytes`. Returns `None` on a header that's too
+    /// short or reports a data offset past the end of the buffer.
+    fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() < 20 {
+            return None;
+        }
+        let data_offset = (data[12] >> 4) as usize * 4;
+        if data_offset < 20 || data.len() < data_offset {
+            return None;
+        }
+        let flags = data[13];
+        let options = &data[20..data_offset];
+
+        Some(Self {
+            src_port: u16::from_be_bytes([data[0], data[1]]),
+            dst_port: u16::from_be_bytes([data[2], data[3]]),
+            seq: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            ack: u32::from_be_bytes([data[8], data[9], data[10], data[11]]),
+            syn: flags & 0x02 != 0,
+            ack_flag: flags & 0x10 != 0,
+            fin: flags & 0x01 != 0,
+            rst: flags & 0x04 != 0,
+            checksum: u16::from_be_bytes([data[16], data[17]]),
+            payload: data[data_offset..].to_vec(),
+            sack_blocks: parse_sack_option(options),
+            timestamp: None,
+            delay_sample: None,
+        })
+    }
+}
+
+/// Scans the TCP options area for a SACK block (kind 5), skipping NOPs
+/// (kind 1) and other options by their length byte. Returns the blocks
+/// found, or an empty `Vec` if there's no SACK option.
+fn parse_sack_option(options: &[u8]) -> Vec<(u32, u32)> {
+    let mut i = 0;
+    while i < options.len() {
+        match options[i] {
+            0 => break,
+            1 => i += 1,
+            5 => {
+                let len = options.get(i + 1).copied().unwrap_or(2) as usize;
+                let mut blocks = Vec::new();
+                let mut j = i + 2;
+                while j + 8 <= i + len && j + 8 <= options.len() {
+                    let start = u32::from_be_bytes(options[j..j + 4].try_into().unwrap());
+                    let end = u32::from_be_bytes(options[j + 4..j + 8].try_into().unwrap());
+                    blocks.push((start, end));
+                    j += 8;
+                }
+                return blocks;
+            }
+            _ => {
+                let len = options.get(i + 1).copied().unwrap_or(2).max(2) as usize;
+                i += len;
+            }
+        }
+    }
+    Vec::new()
+}
+
+/// Builds an IPv4 header (version 4, no options, TTL 64) around a TCP
+/// segment's bytes so it can be injected onto the wire via a raw socket,
+/// reusing the same `src_ip`/`dst_ip` already threaded through
+/// `TcpSegment::with_checksum` for the pseudo-header.
+fn build_ipv4_packet(segment: &TcpSegment, src_ip: [u8; 4], dst_ip: [u8; 4]) -> Vec<u8> {
+    let mut tcp_bytes = segment.to_bytes();
+    tcp_bytes.extend_from_slice(&segment.payload);
+
+    let total_len = 20 + tcp_bytes.len();
+    let mut packet = vec![0u8; 20];
+    packet[0] = 0x45;
+    packet[2..4].copy_from_slice(&(total_len as u16).to_be_bytes());
+    packet[6..8].copy_from_slice(&0x4000u16.to_be_bytes()); // don't fragment
+    packet[8] = 64; // TTL
+    packet[9] = 6; // protocol: TCP
+    packet[12..16].copy_from_slice(&src_ip);
+    packet[16..20].copy_from_slice(&dst_ip);
+    let ip_checksum = checksum(&packet);
+    packet[10..12].copy_from_slice(&ip_checksum.to_be_bytes());
+
+    packet.extend_from_slice(&tcp_bytes);
+    packet
+}
+
+/// Prepends a 14-byte Ethernet header (destination MAC, source MAC, then
+/// the IPv4 ethertype) to an IPv4 packet. `SOCK_RAW` packet sockets hand
+/// `send`/`recv` the complete L2 frame, not just the IP payload, so this
+/// is what `raw_socket::RawSocket::recv_segment` expects to find and
+/// strip on the way back in.
+fn build_ethernet_frame(ip_packet: &[u8], src_mac: [u8; 6], dst_mac: [u8; 6]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(14 + ip_packet.len());
+    frame.extend_from_slice(&dst_mac);
+    frame.extend_from_slice(&src_mac);
+    frame.extend_from_slice(&0x0800u16.to_be_bytes());
+    frame.extend_from_slice(ip_packet);
+    frame
 }
 
 fn checksum(data: &[u8]) -> u16 {
@@ -101,24 +317,257 @@ fn checksum(data: &[u8]) -> u16 {
 }
 
 fn compute_tcp_checksum(segment: &TcpSegment, src_ip: [u8; 4], dst_ip: [u8; 4]) -> u16 {
-    let tcp_len = 20 + segment.payload.len();
+    let header = segment.to_bytes();
+    let tcp_len = header.len() + segment.payload.len();
     let mut pseudo = Vec::with_capacity(12 + tcp_len);
     pseudo.extend_from_slice(&src_ip);
     pseudo.extend_from_slice(&dst_ip);
     pseudo.push(0);
     pseudo.push(6);
     pseudo.extend_from_slice(&(tcp_len as u16).to_be_bytes());
-    pseudo.extend_from_slice(&segment.to_bytes());
+    pseudo.extend_from_slice(&header);
     pseudo.extend_from_slice(&segment.payload);
     checksum(&pseudo)
 }
 
+/// Optional transport backend that puts real bytes on the wire through a
+/// Linux `AF_PACKET` raw socket, instead of the in-process [`Network`]
+/// simulator. Linux-only since `AF_PACKET` is a Linux-specific address
+/// family; the FFI surface is hand-declared rather than pulling in the
+/// `libc` crate. Opening the socket needs `CAP_NET_RAW` (or root), so
+/// callers treat a failed `open()` as a runtime-environment limitation,
+/// not a bug — see `simulate_raw_socket_roundtrip` below.
+#[cfg(target_os = "linux")]
+mod raw_socket {
+    use super::{build_ethernet_frame, build_ipv4_packet, TcpSegment};
+    use std::ffi::CString;
+    use std::io;
+
+    const AF_PACKET: i32 = 17;
+    const SOCK_RAW: i32 = 3;
+    const ETH_P_IP: u16 = 0x0800;
+    const ETH_HEADER_LEN: usize = 14;
+    const IP_HEADER_MIN_LEN: usize = 20;
+
+    /// Mirrors `struct sockaddr_ll` from `<linux/if_packet.h>`.
+    #[repr(C)]
+    struct SockaddrLl {
+        sll_family: u16,
+        sll_protocol: u16,
+        sll_ifindex: i32,
+        sll_hatype: u16,
+        sll_pkttype: u8,
+        sll_halen: u8,
+        sll_addr: [u8; 8],
+    }
+
+    extern "C" {
+        fn socket(domain: i32, ty: i32, protocol: i32) -> i32;
+        fn close(fd: i32) -> i32;
+        fn bind(fd: i32, addr: *const SockaddrLl, len: u32) -> i32;
+        fn sendto(
+            fd: i32,
+            buf: *const u8,
+            len: usize,
+            flags: i32,
+            addr: *const SockaddrLl,
+            addrlen: u32,
+        ) -> isize;
+        fn recv(fd: i32, buf: *mut u8, len: usize, flags: i32) -> isize;
+        fn if_nametoindex(ifname: *const u8) -> u32;
+    }
+
+    /// A raw `AF_PACKET`/`SOCK_RAW` socket bound to one named interface
+    /// (e.g. `"lo"`), used to emit and capture full Ethernet frames that
+    /// carry our [`TcpSegment`] wire format. `SOCK_RAW` hands the kernel
+    /// (and expects back) complete L2 frames, not bare IP packets, so
+    /// every send goes out via `sendto` with an explicit `sockaddr_ll`
+    /// naming the interface and destination MAC.
+    pub struct RawSocket {
+        fd: i32,
+        ifindex: i32,
+        ring: Vec<u8>,
+    }
+
+    impl RawSocket {
+        /// Resolves `ifname` (e.g. `"lo"`) to an interface index, then
+        /// opens and binds a raw socket to it. Requires `CAP_NET_RAW` (or
+        /// root).
+        pub fn open(ifname: &str) -> io::Result<Self> {
+            let c_ifname = CString::new(ifname)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "interface name contains a NUL byte"))?;
+            let ifindex = unsafe { if_nametoindex(c_ifname.as_ptr() as *const u8) };
+            if ifindex == 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let protocol = ETH_P_IP.to_be() as i32;
+            let fd = unsafe { socket(AF_PACKET, SOCK_RAW, protocol) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let addr = SockaddrLl {
+                sll_family: AF_PACKET as u16,
+                sll_protocol: ETH_P_IP.to_be(),
+                sll_ifindex: ifindex as i32,
+                sll_hatype: 0,
+                sll_pkttype: 0,
+                sll_halen: 0,
+                sll_addr: [0; 8],
+            };
+            let bound = unsafe {
+                bind(
+                    fd,
+                    &addr,
+                    std::mem::size_of::<SockaddrLl>() as u32,
+                )
+            };
+            if bound < 0 {
+                let err = io::Error::last_os_error();
+                unsafe {
+                    close(fd);
+                }
+                return Err(err);
+            }
+            Ok(Self {
+                fd,
+                ifindex: ifindex as i32,
+                ring: vec![0u8; 65536],
+            })
+        }
+
+        /// Wraps `segment` in an IPv4 header and an Ethernet header
+        /// addressed to `dst_mac`, then hands the complete frame to
+        /// `sendto` on this socket's bound interface.
+        pub fn send_segment(
+            &self,
+            segment: &TcpSegment,
+            src_ip: [u8; 4],
+            dst_ip: [u8; 4],
+            src_mac: [u8; 6],
+            dst_mac: [u8; 6],
+        ) -> io::Result<()> {
+            let ip_packet = build_ipv4_packet(segment, src_ip, dst_ip);
+            let frame = build_ethernet_frame(&ip_packet, src_mac, dst_mac);
+
+            let mut sll_addr = [0u8; 8];
+            sll_addr[..6].copy_from_slice(&dst_mac);
+            let dest = SockaddrLl {
+                sll_family: AF_PACKET as u16,
+                sll_protocol: ETH_P_IP.to_be(),
+                sll_ifindex: self.ifindex,
+                sll_hatype: 0,
+                sll_pkttype: 0,
+                sll_halen: 6,
+                sll_addr,
+            };
+
+            let written = unsafe {
+                sendto(
+                    self.fd,
+                    frame.as_ptr(),
+                    frame.len(),
+                    0,
+                    &dest,
+                    std::mem::size_of::<SockaddrLl>() as u32,
+                )
+            };
+            if written < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+
+        /// Reads one frame off the wire, strips the Ethernet and IP
+        /// headers, and decodes the remainder as a [`TcpSegment`].
+        /// Returns `Ok(None)` for frames that aren't IPv4/TCP or are too
+        /// short to hold a full segment.
+        pub fn recv_segment(&mut self) -> io::Result<Option<TcpSegment>> {
+            let n = unsafe { recv(self.fd, self.ring.as_mut_ptr(), self.ring.len(), 0) };
+            if n < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let frame = &self.ring[..n as usize];
+            if frame.len() < ETH_HEADER_LEN + IP_HEADER_MIN_LEN {
+                return Ok(None);
+            }
+            let ethertype = u16::from_be_bytes([frame[12], frame[13]]);
+            if ethertype != ETH_P_IP {
+                return Ok(None);
+            }
+            let ip = &frame[ETH_HEADER_LEN..];
+            let ihl = (ip[0] & 0x0F) as usize * 4;
+            if ip.len() < ihl || ip[9] != 6 {
+                return Ok(None);
+            }
+            Ok(TcpSegment::from_bytes(&ip[ihl..]))
+        }
+    }
+
+    impl Drop for RawSocket {
+        fn drop(&mut self) {
+            unsafe {
+                close(self.fd);
+            }
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 enum TcpState {
     Closed,
     SynSent,
     SynReceived,
     Established,
+    FinWait1,
+    FinWait2,
+    CloseWait,
+    LastAck,
+    Closing,
+    TimeWait,
+}
+
+const TIME_WAIT_DURATION: Duration = Duration::from_millis(500);
+
+/// CUBIC window-growth constants (RFC 8312 defaults).
+const CUBIC_C: f64 = 0.4;
+const CUBIC_BETA: f64 = 0.7;
+
+/// Floor for the retransmission timeout, per Jacobson/Karels (RFC 6298
+/// recommends 1s, but this simulator runs on a compressed clock).
+const MIN_RTO: Duration = Duration::from_millis(200);
+
+/// LEDBAT constants, as used by micro-transport/uTP-style background flows.
+const LEDBAT_TARGET: Duration = Duration::from_millis(100);
+const LEDBAT_GAIN: f64 = 1.0;
+const LEDBAT_BASE_HISTORY: Duration = Duration::from_secs(60);
+/// Nominal segment size used to turn `bytes_acked` into the same
+/// segment-denominated units `cwnd` uses elsewhere in this simulator.
+const LEDBAT_MSS: f64 = 1000.0;
+
+/// Per-segment queuing delay `Network` adds for every segment already
+/// sitting in its delivery queue, modeling a shared bottleneck: a flow
+/// sharing `Network` with another lengthens both flows' one-way delay,
+/// which is what lets a concurrent LEDBAT flow detect and cede to it.
+const QUEUE_DELAY_PER_SEGMENT: Duration = Duration::from_millis(15);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CongestionControl {
+    NewReno,
+    Cubic,
+    Ledbat,
+}
+
+/// A segment sitting in the sender's retransmit queue, along with enough
+/// bookkeeping to apply Karn's algorithm and exponential RTO backoff.
+struct InFlightSegment {
+    segment: TcpSegment,
+    sent_at: Instant,
+    retransmitted: bool,
+    backoff: u32,
+    /// Set once a SACK block from the receiver covers this segment, so
+    /// `tick()` stops retransmitting data that already arrived out of order.
+    sacked: bool,
 }
 
 struct TcpClient {
@@ -127,8 +576,24 @@ struct TcpClient {
     seq: u32,
     ack: u32,
     peer_seq: u32,
-    window: VecDeque<TcpSegment>,
-    window_size: usize,
+    window: VecDeque<InFlightSegment>,
+    cwnd: f64,
+    ssthresh: f64,
+    highest_ack: u32,
+    dup_acks: u32,
+    algorithm: CongestionControl,
+    w_max: f64,
+    t0: Instant,
+    srtt: Option<f64>,
+    rttvar: f64,
+    rto: Duration,
+    /// Receiver-side out-of-order buffer, keyed by the segment's starting
+    /// sequence number, used to build SACK blocks above the cumulative ack.
+    recv_buffer: BTreeMap<u32, Vec<u8>>,
+    /// LEDBAT: minimum one-way delay observed over `LEDBAT_BASE_HISTORY`.
+    base_delay: Duration,
+    /// LEDBAT: recent `(observed_at, delay)` samples backing `base_delay`.
+    delay_samples: VecDeque<(Instant, Duration)>,
 }
 
 impl TcpClient {
@@ -140,16 +605,52 @@ impl TcpClient {
             ack: 0,
             peer_seq: 0,
             window: VecDeque::new(),
-            window_size: 5,
+            cwnd: 1.0,
+            ssthresh: 64.0,
+            highest_ack: 0,
+            dup_acks: 0,
+            algorithm: CongestionControl::NewReno,
+            w_max: 1.0,
+            t0: Instant::now(),
+            srtt: None,
+            rttvar: 0.0,
+            rto: MIN_RTO,
+            recv_buffer: BTreeMap::new(),
+            base_delay: Duration::MAX,
+            delay_samples: VecDeque::new(),
         }
     }
 
+    /// Switches this client to LEDBAT delay-based window growth: it backs
+    /// off as queuing delay approaches `LEDBAT_TARGET` instead of waiting
+    /// for loss, so it cedes bandwidth to a concurrent loss-based flow.
+    fn with_ledbat(mut self) -> Self {
+        self.algorithm = CongestionControl::Ledbat;
+        self
+    }
+
+    /// Switches this client to CUBIC window growth instead of the default
+    /// NewReno slow start / congestion avoidance.
+    fn with_cubic(mut self) -> Self {
+        self.algorithm = CongestionControl::Cubic;
+        self.w_max = self.cwnd;
+        self.t0 = Instant::now();
+        self
+    }
+
     fn send_syn(&mut self, to: u16) -> TcpSegment {
         self.state = TcpState::SynSent;
         TcpSegment::syn(self.port, to, self.seq)
     }
 
     fn receive(&mut self, seg: &TcpSegment) -> Option<TcpSegment> {
+        if seg.rst {
+            if self.state != TcpState::Closed {
+                println!("Client {} received RST, aborting connection", self.port);
+            }
+            self.state = TcpState::Closed;
+            return None;
+        }
         match self.state {
             TcpState::Closed => {
                 if seg.syn && !seg.ack_flag {
@@ -184,24 +685,124 @@ impl TcpClient {
                 None
             }
             TcpState::Established => {
+                if seg.fin {
+                    self.ack = seg.seq + 1;
+                    self.state = TcpState::CloseWait;
+                    return Some(TcpSegment::ack(self.port, seg.src_port, self.seq, self.ack));
+                }
                 if !seg.payload.is_empty() {
-                    println!(
-                        "Client {} received data: {:?}",
+                    if seg.seq == self.ack {
+                        println!(
+                            "Client {} received data: {:?}",
+                            self.port,
+                            String::from_utf8_lossy(&seg.payload)
+                        );
+                        self.ack = seg.seq + seg.payload.len() as u32;
+                        while let Some((&next_seq, _)) = self.recv_buffer.iter().next() {
+                            if next_seq != self.ack {
+                                break;
+                            }
+                            let data = self.recv_buffer.remove(&next_seq).unwrap();
+                            self.ack += data.len() as u32;
+                        }
+                    } else if seg.seq > self.ack {
+                        println!(
+                            "Client {} buffered out-of-order segment seq={}",
+                            self.port, seg.seq
+                        );
+                        self.recv_buffer.insert(seg.seq, seg.payload.clone());
+                    }
+                    let reply = TcpSegment::ack_with_sack(
                         self.port,
-                        String::from_utf8_lossy(&seg.payload)
+                        seg.src_port,
+                        self.seq,
+                        self.ack,
+                        self.compute_sack_blocks(),
                     );
-                    self.ack = seg.seq + seg.payload.len() as u32;
+                    return Some(match seg.timestamp {
+                        Some(sent_at) => reply.with_delay_sample(sent_at.elapsed()),
+                        None => reply,
+                    });
+                }
+                None
+            }
+            TcpState::FinWait1 => {
+                if seg.fin {
+                    self.ack = seg.seq + 1;
+                    self.state = TcpState::Closing;
+                    Some(TcpSegment::ack(self.port, seg.src_port, self.seq, self.ack))
+                } else if seg.ack_flag {
+                    self.state = TcpState::FinWait2;
+                    None
+                } else {
+                    None
+                }
+            }
+            TcpState::FinWait2 => {
+                if seg.fin {
+                    self.ack = seg.seq + 1;
+                    let reply = TcpSegment::ack(self.port, seg.src_port, self.seq, self.ack);
+                    self.enter_time_wait();
+                    Some(reply)
+                } else {
+                    None
+                }
+            }
+            TcpState::Closing => {
+                if seg.ack_flag {
+                    self.enter_time_wait();
                 }
                 None
             }
+            TcpState::CloseWait => None,
+            TcpState::LastAck => {
+                if seg.ack_flag {
+                    self.state = TcpState::Closed;
+                    println!("Client {} closed", self.port);
+                }
+                None
+            }
+            TcpState::TimeWait => None,
+        }
+    }
+
+    /// Actively closes the connection (FIN) from `Established`, or finishes
+    /// the passive close (FIN) from `CloseWait` after the peer's FIN was
+    /// already acknowledged.
+    fn send_fin(&mut self, to: u16) -> TcpSegment {
+        match self.state {
+            TcpState::Established => self.state = TcpState::FinWait1,
+            TcpState::CloseWait => self.state = TcpState::LastAck,
+            _ => {}
         }
+        let seg = TcpSegment::fin(self.port, to, self.seq, self.ack);
+        self.seq += 1;
+        seg
+    }
+
+    /// Parks the connection in `TimeWait` for 2*MSL (simulated) before
+    /// returning to `Closed`, per RFC 793.
+    fn enter_time_wait(&mut self) {
+        self.state = TcpState::TimeWait;
+        println!("Client {} entering TIME_WAIT", self.port);
+        thread::sleep(TIME_WAIT_DURATION);
+        self.state = TcpState::Closed;
+        println!("Client {} closed", self.port);
     }
 
     fn send_data(&mut self, to: u16, msg: &str) -> Option<TcpSegment> {
-        if self.window.len() < self.window_size {
-            let seg = TcpSegment::data(self.port, to, self.seq, self.ack, msg.as_bytes().to_vec());
+        if (self.window.len() as f64) < self.cwnd {
+            let now = Instant::now();
+            let seg = TcpSegment::data(self.port, to, self.seq, self.ack, msg.as_bytes().to_vec())
+                .with_timestamp(now);
             self.seq += msg.len() as u32;
-            self.window.push_back(seg.clone());
+            self.window.push_back(InFlightSegment {
+                segment: seg.clone(),
+                sent_at: now,
+                retransmitted: false,
+                backoff: 0,
+                sacked: false,
+            });
             Some(seg)
         } else {
             println!("Window full, waiting to send more");
@@ -209,18 +810,329 @@ impl TcpClient {
         }
     }
 
-    fn ack_data(&mut self, ack_number: u32) {
-        while let Some(front) = self.window.front() {
-            let end_seq = front.seq;
-            if end_seq <= ack_number {
-                self.window.pop_front();
-            } else {
+    fn ack_data(&mut self, ack_number: u32, sack_blocks: &[(u32, u32)], delay_sample: Option<Duration>) {
+        let now = Instant::now();
+        if ack_number > self.highest_ack {
+            let mut bytes_acked = 0u32;
+            while let Some(front) = self.window.front() {
+                let seg_end = front.segment.seq + front.segment.payload.len() as u32;
+                if seg_end > ack_number {
+                    break;
+                }
+                let flight = self.window.pop_front().unwrap();
+                bytes_acked += flight.segment.payload.len() as u32;
+                // Karn's algorithm: never sample RTT from a retransmitted segment,
+                // since an ACK for it could be answering either transmission.
+                if !flight.retransmitted {
+                    let sample_rtt = now.duration_since(flight.sent_at).as_secs_f64();
+                    self.update_rto(sample_rtt);
+                }
+            }
+            self.highest_ack = ack_number;
+            self.dup_acks = 0;
+            self.grow_cwnd(bytes_acked, delay_sample);
+        } else if ack_number == self.highest_ack && ack_number != 0 {
+            self.dup_acks += 1;
+            if self.dup_acks == 3 {
+                self.fast_retransmit();
+            }
+        }
+        self.apply_sack(sack_blocks);
+    }
+
+    /// Marks window segments covered by a SACK block as already received, so
+    /// `tick()` stops retransmitting data the receiver has, leaving only the
+    /// genuinely missing ranges to be resent.
+    fn apply_sack(&mut self, sack_blocks: &[(u32, u32)]) {
+        for flight in self.window.iter_mut() {
+            let seg_end = flight.segment.seq + flight.segment.payload.len() as u32;
+            if sack_blocks
+                .iter()
+                .any(|&(start, end)| flight.segment.seq >= start && seg_end <= end)
+            {
+                flight.sacked = true;
+            }
+        }
+    }
+
+    /// Merges the receiver's out-of-order buffer into up to three contiguous
+    /// `(start, end)` ranges for the SACK option.
+    fn compute_sack_blocks(&self) -> Vec<(u32, u32)> {
+        let mut blocks = Vec::new();
+        let mut iter = self.recv_buffer.iter().peekable();
+        while let Some((&start, data)) = iter.next() {
+            let mut end = start + data.len() as u32;
+            while let Some(&(&next_seq, next_data)) = iter.peek() {
+                if next_seq != end {
+                    break;
+                }
+                end += next_data.len() as u32;
+                iter.next();
+            }
+            blocks.push((start, end));
+            if blocks.len() == 3 {
+                break;
+            }
+        }
+        blocks
+    }
+
+    /// Jacobson/Karels RTO estimation: smooths the RTT sample into `srtt`
+    /// and its mean deviation into `rttvar`, then derives `rto = srtt +
+    /// 4*rttvar`, clamped to `MIN_RTO`.
+    fn update_rto(&mut self, sample_rtt: f64) {
+        match self.srtt {
+            None => {
+                self.srtt = Some(sample_rtt);
+                self.rttvar = sample_rtt / 2.0;
+            }
+            Some(srtt) => {
+                self.rttvar = 0.75 * self.rttvar + 0.25 * (srtt - sample_rtt).abs();
+                self.srtt = Some(0.875 * srtt + 0.125 * sample_rtt);
+            }
+        }
+        let rto = self.srtt.unwrap() + 4.0 * self.rttvar;
+        self.rto = Duration::from_secs_f64(rto).max(MIN_RTO);
+    }
+
+    /// Walks the retransmit queue and re-emits any segment whose age has
+    /// exceeded the current RTO, doubling that segment's backoff (exponential
+    /// backoff) each time it fires again without being acked.
+    fn tick(&mut self, now: Instant) -> Vec<TcpSegment> {
+        let mut expired = Vec::new();
+        for flight in self.window.iter_mut() {
+            if flight.sacked {
+                continue;
+            }
+            let timeout = self.rto * 2u32.pow(flight.backoff.min(6));
+            if now.duration_since(flight.sent_at) >= timeout {
+                flight.retransmitted = true;
+                flight.backoff += 1;
+                flight.sent_at = now;
+                expired.push(flight.segment.clone());
+            }
+        }
+        expired
+    }
+
+    /// NewReno growth: +1 segment per ACK during slow start, +1/cwnd per ACK
+    /// (i.e. roughly +1 per RTT) once `cwnd` reaches `ssthresh`. Under CUBIC,
+    /// `cwnd` instead follows the cubic function of time since the last
+    /// congestion event, floored by the Reno-equivalent window so the flow
+    /// stays TCP-friendly. Under LEDBAT, `cwnd` reacts to the queuing delay
+    /// carried by `delay_sample` rather than to loss.
+    fn grow_cwnd(&mut self, bytes_acked: u32, delay_sample: Option<Duration>) {
+        match self.algorithm {
+            CongestionControl::NewReno => {
+                if self.cwnd < self.ssthresh {
+                    self.cwnd += 1.0;
+                } else {
+                    self.cwnd += 1.0 / self.cwnd;
+                }
+            }
+            CongestionControl::Cubic => {
+                let t = self.t0.elapsed().as_secs_f64();
+                let k = (self.w_max * CUBIC_BETA / CUBIC_C).cbrt();
+                let cubic_cwnd = CUBIC_C * (t - k).powi(3) + self.w_max;
+                let reno_cwnd = self.cwnd + 1.0 / self.cwnd;
+                self.cwnd = cubic_cwnd.max(reno_cwnd).max(1.0);
+            }
+            CongestionControl::Ledbat => {
+                if let Some(delay) = delay_sample {
+                    self.record_delay_sample(delay, bytes_acked);
+                }
+            }
+        }
+        println!(
+            "Client {} cwnd={:.2} ssthresh={:.2}",
+            self.port, self.cwnd, self.ssthresh
+        );
+    }
+
+    /// Folds a one-way delay sample into the rolling `base_delay` estimate,
+    /// then nudges `cwnd` by `GAIN * off_target * bytes_acked / cwnd`, where
+    /// `off_target` goes to zero (and below) as `queuing_delay` approaches
+    /// (and exceeds) `LEDBAT_TARGET`.
+    fn record_delay_sample(&mut self, sample: Duration, bytes_acked: u32) {
+        let now = Instant::now();
+        self.delay_samples.push_back((now, sample));
+        while let Some(&(observed_at, _)) = self.delay_samples.front() {
+            if now.duration_since(observed_at) <= LEDBAT_BASE_HISTORY {
                 break;
             }
+            self.delay_samples.pop_front();
+        }
+        self.base_delay = self
+            .delay_samples
+            .iter()
+            .map(|&(_, d)| d)
+            .min()
+            .unwrap_or(sample);
+
+        let queuing_delay = sample.saturating_sub(self.base_delay);
+        let off_target = (LEDBAT_TARGET.as_secs_f64() - queuing_delay.as_secs_f64())
+            / LEDBAT_TARGET.as_secs_f64();
+        let segments_acked = bytes_acked as f64 / LEDBAT_MSS;
+        self.cwnd += LEDBAT_GAIN * off_target * segments_acked / self.cwnd;
+        self.cwnd = self.cwnd.max(1.0);
+    }
+
+    /// Three duplicate ACKs: halve the window and fast-retransmit instead of
+    /// waiting out a full RTO.
+    fn fast_retransmit(&mut self) {
+        self.on_congestion_event();
+        println!(
+            "Client {} fast retransmit: cwnd={:.2} ssthresh={:.2}",
+            self.port, self.cwnd, self.ssthresh
+        );
+    }
+
+    /// RTO fired with no new ACK: drop back to slow start from a fresh
+    /// `cwnd = 1`, per classic NewReno loss recovery (CUBIC reacts the same
+    /// way as a dup-ACK loss, just via `cwnd = 1` too since the RTO implies
+    /// the whole window was lost).
+    fn on_timeout(&mut self) {
+        self.on_congestion_event();
+        if self.algorithm == CongestionControl::NewReno {
+            self.cwnd = 1.0;
+        }
+        println!(
+            "Client {} timeout: cwnd={:.2} ssthresh={:.2}",
+            self.port, self.cwnd, self.ssthresh
+        );
+    }
+
+    /// Shared loss reaction: NewReno halves via `ssthresh`; CUBIC records
+    /// `w_max` and restarts the cubic clock from the reduced window; LEDBAT
+    /// still halves on an outright loss even though it normally reacts to
+    /// queuing delay instead.
+    fn on_congestion_event(&mut self) {
+        match self.algorithm {
+            CongestionControl::NewReno => {
+                self.ssthresh = (self.cwnd / 2.0).max(2.0);
+                self.cwnd = self.ssthresh;
+            }
+            CongestionControl::Cubic => {
+                self.w_max = self.cwnd;
+                self.cwnd = (self.cwnd * CUBIC_BETA).max(1.0);
+                self.ssthresh = self.cwnd;
+                self.t0 = Instant::now();
+            }
+            CongestionControl::Ledbat => {
+                self.cwnd = (self.cwnd * 0.5).max(1.0);
+                self.ssthresh = self.cwnd;
+            }
+        }
+        self.dup_acks = 0;
+    }
+}
+
+/// Minimal xorshift64* PRNG so `Network` doesn't need an external `rand`
+/// dependency just to roll loss/duplication/reorder decisions.
+struct SimpleRng(u64);
+
+impl SimpleRng {
+    fn new(seed: u64) -> Self {
+        Self(seed | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn next_jitter(&mut self, max: Duration) -> Duration {
+        if max.is_zero() {
+            return Duration::ZERO;
         }
+        Duration::from_nanos((self.next_f64() * max.as_nanos() as f64) as u64)
     }
 }
 
+/// A lossy, reordering, delayed channel sitting between two `TcpClient`s.
+/// Segments handed to `transmit` are queued with a randomized delivery time;
+/// `deliver_due` hands back whatever has "arrived" since it was last polled,
+/// which may be out of order relative to how it was sent.
+struct Network {
+    loss_prob: f64,
+    dup_prob: f64,
+    reorder_window: Duration,
+    latency: Duration,
+    queue: VecDeque<(Instant, TcpSegment)>,
+    rng: SimpleRng,
+}
+
+impl Network {
+    fn new(loss_prob: f64, dup_prob: f64, reorder_window: Duration, latency: Duration) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0x2545F4914F6CDD1D);
+        Self {
+            loss_prob,
+            dup_prob,
+            reorder_window,
+            latency,
+            queue: VecDeque::new(),
+            rng: SimpleRng::new(seed),
+        }
+    }
+
+    fn transmit(&mut self, seg: TcpSegment) {
+        if self.rng.next_f64() < self.loss_prob {
+            println!("Network: dropped segment seq={}", seg.seq);
+            return;
+        }
+
+        let queuing_delay = QUEUE_DELAY_PER_SEGMENT * self.queue.len() as u32;
+        let deliver_at =
+            Instant::now() + self.latency + queuing_delay + self.rng.next_jitter(self.reorder_window);
+        self.queue.push_back((deliver_at, seg.clone()));
+
+        if self.rng.next_f64() < self.dup_prob {
+            println!("Network: duplicated segment seq={}", seg.seq);
+            self.queue.push_back((deliver_at, seg));
+        }
+    }
+
+    /// Returns every segment whose scheduled delivery time has passed,
+    /// scanning the whole queue (not just the front) so jitter can reorder
+    /// segments relative to the order they were sent in.
+    fn deliver_due(&mut self) -> Vec<TcpSegment> {
+        let now = Instant::now();
+        let (due, pending): (VecDeque<_>, VecDeque<_>) =
+            self.queue.drain(..).partition(|(t, _)| *t <= now);
+        self.queue = pending;
+        due.into_iter().map(|(_, seg)| seg).collect()
+    }
+}
+
+/// Transmits `seg` through `network` and polls `deliver_due` until a segment
+/// addressed to `to_port` shows up, or gives up after a short while (which
+/// happens when the network drops it).
+fn send_and_await(network: &mut Network, seg: TcpSegment, to_port: u16) -> Option<TcpSegment> {
+    network.transmit(seg);
+    for _ in 0..20 {
+        thread::sleep(Duration::from_millis(10));
+        if let Some(found) = network
+            .deliver_due()
+            .into_iter()
+            .find(|s| s.dst_port == to_port)
+        {
+            return Some(found);
+        }
+    }
+    None
+}
+
 fn simulate_handshake_with_timeout() {
     let mut client_a = TcpClient::new(1000);
     let mut client_b = TcpClient::new(2000);
@@ -228,6 +1140,8 @@ fn simulate_handshake_with_timeout() {
     let src_ip = [192, 168, 0, 1];
     let dst_ip = [192, 168, 0, 2];
 
+    let mut network = Network::new(0.1, 0.05, Duration::from_millis(30), Duration::from_millis(20));
+
     let mut tries = 0;
     let max_tries = 3;
     let mut handshake_done = false;
@@ -237,12 +1151,20 @@ fn simulate_handshake_with_timeout() {
         let syn = client_a.send_syn(client_b.port);
         let syn = syn.with_checksum(src_ip, dst_ip);
 
-        if let Some(syn_ack) = client_b.receive(&syn) {
-            println!("SYN-ACK received");
-            let ack = client_a.receive(&syn_ack).unwrap();
-            client_b.receive(&ack);
-            handshake_done = true;
-        } else {
+        if let Some(syn) = send_and_await(&mut network, syn, client_b.port) {
+            if let Some(syn_ack) = client_b.receive(&syn) {
+                println!("SYN-ACK received");
+                if let Some(syn_ack) = send_and_await(&mut network, syn_ack, client_a.port) {
+                    let ack = client_a.receive(&syn_ack).unwrap();
+                    if let Some(ack) = send_and_await(&mut network, ack, client_b.port) {
+                        client_b.receive(&ack);
+                        handshake_done = true;
+                    }
+                }
+            }
+        }
+
+        if !handshake_done {
             println!("Timeout or no response. Retrying...");
         }
 
@@ -252,21 +1174,410 @@ fn simulate_handshake_with_timeout() {
 
     if handshake_done {
         println!("\n3-Way handshake completed!");
+        simulate_data_transfer(&mut network, &mut client_a, &mut client_b);
+        simulate_connection_close(&mut client_a, &mut client_b);
+    } else {
+        println!("Handshake failed after {} tries", max_tries);
+    }
+}
 
-        if let Some(msg) = client_a.send_data(client_b.port, "Hello from A") {
-            client_b.receive(&msg);
-            client_a.ack_data(client_b.ack);
+/// Sends several data segments from `client_a` to `client_b` (and a reply
+/// back) through the lossy `network` so the congestion controller reacts to
+/// real drops and reordering instead of a perfect in-process handoff.
+fn simulate_data_transfer(network: &mut Network, client_a: &mut TcpClient, client_b: &mut TcpClient) {
+    println!("\nSending data under congestion control:");
+    let messages = [
+        "Hello from A, segment 1",
+        "Hello from A, segment 2",
+        "Hello from A, segment 3",
+        "Hello from A, segment 4",
+        "Hello from A, segment 5",
+        "Hello from A, segment 6",
+    ];
+    let mut next_msg = 0;
+
+    for _ in 0..80 {
+        while next_msg < messages.len() {
+            match client_a.send_data(client_b.port, messages[next_msg]) {
+                Some(seg) => {
+                    network.transmit(seg);
+                    next_msg += 1;
+                }
+                None => break,
+            }
         }
 
-        if let Some(reply) = client_b.send_data(client_a.port, "Hi from B") {
+        thread::sleep(Duration::from_millis(15));
+
+        for incoming in network.deliver_due() {
+            if incoming.dst_port == client_b.port {
+                if let Some(ack) = client_b.receive(&incoming) {
+                    network.transmit(ack);
+                }
+            } else if incoming.dst_port == client_a.port {
+                client_a.ack_data(incoming.ack, &incoming.sack_blocks, incoming.delay_sample);
+            }
+        }
+
+        for stale in client_a.tick(Instant::now()) {
+            println!("RTO fired for seq={}, retransmitting (rto={:?})", stale.seq, client_a.rto);
+            client_a.on_timeout();
+            network.transmit(stale);
+        }
+
+        if next_msg == messages.len() && client_a.window.is_empty() {
+            break;
+        }
+    }
+
+    if let Some(reply) = client_b.send_data(client_a.port, "Hi from B") {
+        if let Some(reply) = send_and_await(network, reply, client_a.port) {
             client_a.receive(&reply);
-            client_b.ack_data(client_a.ack);
+            client_b.ack_data(client_a.ack, &[], None);
         }
-    } else {
-        println!("Handshake failed after {} tries", max_tries);
+    }
+}
+
+/// Drives the full four-way close: `client_a` initiates with FIN, `client_b`
+/// acks and later sends its own FIN, `client_a` acks it and sits in
+/// `TimeWait` before both sides settle in `Closed`.
+fn simulate_connection_close(client_a: &mut TcpClient, client_b: &mut TcpClient) {
+    println!("\nClosing connection...");
+
+    let fin = client_a.send_fin(client_b.port);
+    let ack = client_b.receive(&fin).unwrap();
+    client_a.receive(&ack);
+
+    let fin = client_b.send_fin(client_a.port);
+    let ack = client_a.receive(&fin).unwrap();
+    client_b.receive(&ack);
+}
+
+/// Demonstrates an abrupt abort: `client_b` sends a RST instead of going
+/// through the four-way close, and `client_a` drops straight to `Closed`
+/// from whatever state it was in.
+fn simulate_rst_abort(client_a: &mut TcpClient, client_b: &TcpClient) {
+    println!("\nAborting connection with RST...");
+    let rst = TcpSegment::rst(client_b.port, client_a.port, client_b.seq);
+    client_a.receive(&rst);
+    println!("Client {} state after RST: {:?}", client_a.port, client_a.state);
+}
+
+/// Demonstrates CUBIC's concave-then-convex window growth across a few
+/// simulated RTTs, contrasting it with the NewReno client used elsewhere.
+fn simulate_cubic_growth() {
+    println!("\nCUBIC congestion window over time:");
+    let mut client = TcpClient::new(3000).with_cubic();
+    client.w_max = 32.0;
+    client.cwnd = (client.w_max * (1.0 - CUBIC_BETA)).max(1.0);
+
+    for rtt in 0..5 {
+        thread::sleep(Duration::from_millis(100));
+        client.grow_cwnd(0, None);
+        println!("  after simulated RTT {}: cwnd={:.2}", rtt + 1, client.cwnd);
+    }
+}
+
+/// Demonstrates a LEDBAT flow backing off as queuing delay climbs toward
+/// `LEDBAT_TARGET`, then recovering once the queue drains — the behavior
+/// that lets it cede bandwidth to a concurrent loss-based flow.
+fn simulate_ledbat_background_flow() {
+    println!("\nLEDBAT cwnd vs. queuing delay:");
+    let mut client = TcpClient::new(4000).with_ledbat();
+    client.cwnd = 4.0;
+
+    let delays_ms = [10, 40, 80, 100, 120, 150, 90, 20];
+    for delay_ms in delays_ms {
+        client.grow_cwnd(1000, Some(Duration::from_millis(delay_ms)));
+        println!(
+            "  one-way delay={}ms -> cwnd={:.2} (base_delay={:?})",
+            delay_ms, client.cwnd, client.base_delay
+        );
+    }
+}
+
+/// Start/peak/end `cwnd` snapshots from [`run_ledbat_vs_newreno`], shared by
+/// the demo and its unit test so neither has to re-derive the other's
+/// pass/fail story from raw numbers.
+struct LedbatVsNewRenoResult {
+    reno_cwnd_start: f64,
+    reno_cwnd_end: f64,
+    ledbat_cwnd_start: f64,
+    ledbat_cwnd_peak: f64,
+    ledbat_cwnd_end: f64,
+}
+
+/// Runs a NewReno flow and a LEDBAT flow through the same `Network` at
+/// once, each to its own receiver, so the queuing delay one flow adds to
+/// the shared queue is visible to the other. Demonstrates LEDBAT's whole
+/// point: it reads that rising delay and backs off, ceding the link to
+/// the loss-based flow instead of fighting it for bandwidth.
+fn run_ledbat_vs_newreno(rounds: u32) -> LedbatVsNewRenoResult {
+    let mut network = Network::new(0.0, 0.0, Duration::from_millis(5), Duration::from_millis(10));
+
+    let mut reno = TcpClient::new(7000);
+    let mut reno_peer = TcpClient::new(7001);
+    reno.state = TcpState::Established;
+    reno_peer.state = TcpState::Established;
+    reno_peer.ack = reno.seq;
+
+    let mut ledbat = TcpClient::new(7002).with_ledbat();
+    ledbat.cwnd = 4.0;
+    let mut ledbat_peer = TcpClient::new(7003);
+    ledbat.state = TcpState::Established;
+    ledbat_peer.state = TcpState::Established;
+    ledbat_peer.ack = ledbat.seq;
+
+    // Full-size (MSS-equivalent) payloads so `bytes_acked` actually drives
+    // `grow_cwnd` by whole segments, same as the real traffic LEDBAT's math
+    // in `record_delay_sample` is scaled for.
+    let reno_payload = "x".repeat(LEDBAT_MSS as usize);
+    let ledbat_payload = "y".repeat(LEDBAT_MSS as usize);
+
+    let reno_cwnd_start = reno.cwnd;
+    let ledbat_cwnd_start = ledbat.cwnd;
+    let mut ledbat_cwnd_peak = ledbat.cwnd;
+
+    for _round in 0..rounds {
+        // NewReno fills its whole window every round, building up the
+        // shared queue as cwnd grows.
+        while let Some(seg) = reno.send_data(reno_peer.port, &reno_payload) {
+            network.transmit(seg);
+        }
+        // LEDBAT sends as a lighter background flow.
+        if let Some(seg) = ledbat.send_data(ledbat_peer.port, &ledbat_payload) {
+            network.transmit(seg);
+        }
+
+        thread::sleep(Duration::from_millis(10));
+
+        for incoming in network.deliver_due() {
+            if incoming.dst_port == reno_peer.port {
+                if let Some(ack) = reno_peer.receive(&incoming) {
+                    network.transmit(ack);
+                }
+            } else if incoming.dst_port == reno.port {
+                reno.ack_data(incoming.ack, &incoming.sack_blocks, incoming.delay_sample);
+            } else if incoming.dst_port == ledbat_peer.port {
+                if let Some(ack) = ledbat_peer.receive(&incoming) {
+                    network.transmit(ack);
+                }
+            } else if incoming.dst_port == ledbat.port {
+                ledbat.ack_data(incoming.ack, &incoming.sack_blocks, incoming.delay_sample);
+                ledbat_cwnd_peak = ledbat_cwnd_peak.max(ledbat.cwnd);
+            }
+        }
+
+        for stale in reno.tick(Instant::now()) {
+            reno.on_timeout();
+            network.transmit(stale);
+        }
+    }
+
+    LedbatVsNewRenoResult {
+        reno_cwnd_start,
+        reno_cwnd_end: reno.cwnd,
+        ledbat_cwnd_start,
+        ledbat_cwnd_peak,
+        ledbat_cwnd_end: ledbat.cwnd,
+    }
+}
+
+fn simulate_ledbat_yields_to_newreno() {
+    println!("\nLEDBAT ceding bandwidth to a concurrent NewReno flow:");
+    let result = run_ledbat_vs_newreno(220);
+    println!(
+        "  NewReno cwnd: {:.2} -> {:.2}   LEDBAT cwnd: {:.2} -> peaked {:.2} -> {:.2}",
+        result.reno_cwnd_start,
+        result.reno_cwnd_end,
+        result.ledbat_cwnd_start,
+        result.ledbat_cwnd_peak,
+        result.ledbat_cwnd_end
+    );
+}
+
+/// Sends one data segment to `peer_port` through a real `AF_PACKET` socket
+/// bound to the loopback interface, instead of the in-process [`Network`],
+/// then tries to capture it back off the wire and feed it into
+/// `client.receive()`. Loopback frames carry an all-zero Ethernet
+/// source/destination address, which is what this builds. Opening a raw
+/// socket needs `CAP_NET_RAW`, which most build/test environments don't
+/// grant, so a failure to open or send is logged and treated as a skip
+/// rather than a panic.
+#[cfg(target_os = "linux")]
+fn simulate_raw_socket_roundtrip(client: &mut TcpClient, peer_port: u16, src_ip: [u8; 4], dst_ip: [u8; 4]) {
+    println!("\nRaw socket round-trip:");
+    let mut sock = match raw_socket::RawSocket::open("lo") {
+        Ok(sock) => sock,
+        Err(e) => {
+            println!("  raw socket unavailable (needs CAP_NET_RAW): {e}");
+            return;
+        }
+    };
+
+    let Some(seg) = client.send_data(peer_port, "raw hello") else {
+        println!("  congestion window closed, nothing to send");
+        return;
+    };
+    let seg = seg.with_checksum(src_ip, dst_ip);
+
+    let loopback_mac = [0u8; 6];
+    if let Err(e) = sock.send_segment(&seg, src_ip, dst_ip, loopback_mac, loopback_mac) {
+        println!("  send failed: {e}");
+        return;
+    }
+
+    match sock.recv_segment() {
+        Ok(Some(captured)) => {
+            println!("  captured segment seq={} back off the wire", captured.seq);
+            client.receive(&captured);
+        }
+        Ok(None) => println!("  captured frame wasn't a decodable IPv4/TCP segment"),
+        Err(e) => println!("  recv failed: {e}"),
     }
 }
 
 fn main() {
     simulate_handshake_with_timeout();
+
+    let mut client_a = TcpClient::new(6000);
+    let client_b = TcpClient::new(6001);
+    client_a.state = TcpState::Established;
+    simulate_rst_abort(&mut client_a, &client_b);
+
+    simulate_cubic_growth();
+    simulate_ledbat_background_flow();
+    simulate_ledbat_yields_to_newreno();
+
+    #[cfg(target_os = "linux")]
+    {
+        let mut client = TcpClient::new(5000);
+        simulate_raw_socket_roundtrip(&mut client, 5001, [127, 0, 0, 1], [127, 0, 0, 1]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ledbat_backs_off_once_queuing_delay_exceeds_target() {
+        let mut client = TcpClient::new(4000).with_ledbat();
+        client.cwnd = 4.0;
+
+        // Well under LEDBAT_TARGET: off_target is positive, cwnd grows.
+        client.grow_cwnd(1000, Some(Duration::from_millis(10)));
+        let cwnd_under_target = client.cwnd;
+        assert!(cwnd_under_target > 4.0);
+
+        // Queuing delay now well past LEDBAT_TARGET above the base delay:
+        // off_target goes negative and cwnd should shrink back down.
+        client.grow_cwnd(1000, Some(Duration::from_millis(150)));
+        assert!(
+            client.cwnd < cwnd_under_target,
+            "cwnd {} should have backed off from {}",
+            client.cwnd,
+            cwnd_under_target
+        );
+    }
+
+    #[test]
+    fn ledbat_yields_to_concurrent_newreno_flow() {
+        // Drives the same two-flow-through-one-`Network` scenario as
+        // `simulate_ledbat_yields_to_newreno`: a LEDBAT flow should cede
+        // bandwidth to a concurrent NewReno flow sharing the same
+        // bottleneck queue, not just when fed synthetic delay samples
+        // directly.
+        let result = run_ledbat_vs_newreno(220);
+
+        assert!(
+            result.reno_cwnd_end > 10.0,
+            "NewReno cwnd {} should have grown substantially unopposed",
+            result.reno_cwnd_end
+        );
+        assert!(
+            result.ledbat_cwnd_peak > result.ledbat_cwnd_start,
+            "LEDBAT cwnd should climb above its start before the shared queue backs it off"
+        );
+        assert!(
+            result.ledbat_cwnd_end < result.ledbat_cwnd_peak,
+            "LEDBAT cwnd {} should have backed off from its peak {} once the NewReno \
+             flow built up queuing delay in the shared Network",
+            result.ledbat_cwnd_end,
+            result.ledbat_cwnd_peak
+        );
+    }
+
+    #[test]
+    fn cubic_overtakes_reno_equivalent_window_past_k() {
+        let mut client = TcpClient::new(3000).with_cubic();
+        client.w_max = 32.0;
+        client.cwnd = (client.w_max * (1.0 - CUBIC_BETA)).max(1.0);
+
+        // What NewReno would have done with this single ACK, from the same
+        // post-loss starting window.
+        let reno_cwnd = client.cwnd + 1.0 / client.cwnd;
+
+        // Put the cubic clock well past K, into the convex region where
+        // cwnd climbs back above w_max.
+        client.t0 = Instant::now() - Duration::from_secs(5);
+        client.grow_cwnd(0, None);
+
+        assert!(
+            client.cwnd > reno_cwnd,
+            "cubic cwnd {} should exceed the reno-equivalent window {}",
+            client.cwnd,
+            reno_cwnd
+        );
+        assert!(
+            client.cwnd > client.w_max,
+            "cubic cwnd {} should have climbed back past w_max {} post-K",
+            client.cwnd,
+            client.w_max
+        );
+    }
+
+    #[test]
+    fn rto_converges_toward_steady_state_sample_rtt() {
+        let mut client = TcpClient::new(1000);
+
+        for _ in 0..50 {
+            client.update_rto(0.3); // a steady 300ms RTT, no jitter
+        }
+
+        let rto_ms = client.rto.as_millis();
+        assert!(
+            (290..=320).contains(&rto_ms),
+            "rto should settle near the steady 300ms sample, got {rto_ms}ms"
+        );
+    }
+
+    #[test]
+    fn sack_avoids_retransmitting_already_received_segments() {
+        let mut client_a = TcpClient::new(1000);
+        let mut client_b = TcpClient::new(2000);
+        client_a.state = TcpState::Established;
+        client_b.state = TcpState::Established;
+        client_a.cwnd = 3.0;
+        client_b.ack = client_a.seq;
+
+        let seg1 = client_a.send_data(client_b.port, "AAAA").unwrap();
+        let seg2 = client_a.send_data(client_b.port, "BBBB").unwrap(); // lost in transit
+        let seg3 = client_a.send_data(client_b.port, "CCCC").unwrap();
+
+        let ack1 = client_b.receive(&seg1).unwrap();
+        client_a.ack_data(ack1.ack, &ack1.sack_blocks, ack1.delay_sample);
+
+        // seg2 never arrives; seg3 reaches client_b out of order.
+        let ack3 = client_b.receive(&seg3).unwrap();
+        assert_eq!(ack3.ack, seg2.seq);
+        assert_eq!(ack3.sack_blocks, vec![(seg3.seq, seg3.seq + 4)]);
+        client_a.ack_data(ack3.ack, &ack3.sack_blocks, ack3.delay_sample);
+
+        let later = Instant::now() + client_a.rto + Duration::from_millis(1);
+        let retransmitted = client_a.tick(later);
+
+        assert_eq!(retransmitted.len(), 1);
+        assert_eq!(retransmitted[0].seq, seg2.seq);
+    }
 }